@@ -0,0 +1,109 @@
+use super::test_case::TestCase;
+use crate::lang::SgLang;
+
+use ast_grep_config::RuleCollection;
+
+use std::io::Write;
+
+/// Whether a rule has any test case at all. This is rule-level coverage,
+/// not branch-level: we don't walk a rule's composite `all`/`any`/`not`
+/// structure to tell which sub-rule a snippet actually exercised, so "tested"
+/// only means "at least one `valid` or `invalid` case references this id".
+pub struct RuleCoverage {
+  pub id: String,
+  pub has_cases: bool,
+}
+
+/// whether any test case references this rule id at all, `valid` or `invalid`
+fn has_cases(cases: &[TestCase], id: &str) -> bool {
+  cases.iter().any(|c| c.id == id)
+}
+
+pub fn compute_coverage(
+  rules: &RuleCollection<SgLang>,
+  test_cases: &[TestCase],
+) -> Vec<RuleCoverage> {
+  rules
+    .ids()
+    .map(|id| RuleCoverage {
+      id: id.to_string(),
+      has_cases: has_cases(test_cases, id),
+    })
+    .collect()
+}
+
+pub fn print_coverage_table(out: &mut impl Write, coverage: &[RuleCoverage]) -> std::io::Result<()> {
+  writeln!(out, "{:<40}{:>12}", "rule", "status")?;
+  for rc in coverage {
+    let status = if rc.has_cases { "tested" } else { "uncovered" };
+    writeln!(out, "{:<40}{:>12}", rc.id, status)?;
+  }
+  Ok(())
+}
+
+/// ratio of rules that have at least one test case, as a percentage
+pub fn overall_ratio(coverage: &[RuleCoverage]) -> f64 {
+  if coverage.is_empty() {
+    return 100.0;
+  }
+  let covered = coverage.iter().filter(|rc| rc.has_cases).count();
+  covered as f64 / coverage.len() as f64 * 100.0
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_config::{from_str, GlobalRules, RuleConfig};
+
+  fn rule_collection(ids: &[&str]) -> RuleCollection<SgLang> {
+    let globals = GlobalRules::default();
+    let rules = ids
+      .iter()
+      .map(|id| {
+        let yaml = format!(
+          "
+id: {id}
+message: test
+severity: hint
+language: TypeScript
+rule:
+  any: []
+"
+        );
+        let inner = from_str(&yaml).unwrap();
+        RuleConfig::try_from(inner, &globals).unwrap()
+      })
+      .collect();
+    RuleCollection::try_new(rules).expect("RuleCollection must be valid")
+  }
+
+  fn case(id: &str) -> TestCase {
+    TestCase {
+      id: id.to_string(),
+      valid: vec!["1".to_string()],
+      invalid: vec![],
+    }
+  }
+
+  #[test]
+  fn test_compute_coverage_marks_tested_and_uncovered() {
+    let rules = rule_collection(&["has-case", "no-case"]);
+    let coverage = compute_coverage(&rules, &[case("has-case")]);
+    let has_case = coverage.iter().find(|rc| rc.id == "has-case").unwrap();
+    let no_case = coverage.iter().find(|rc| rc.id == "no-case").unwrap();
+    assert!(has_case.has_cases);
+    assert!(!no_case.has_cases);
+  }
+
+  #[test]
+  fn test_overall_ratio() {
+    let rules = rule_collection(&["has-case", "no-case"]);
+    let coverage = compute_coverage(&rules, &[case("has-case")]);
+    assert_eq!(overall_ratio(&coverage), 50.0);
+  }
+
+  #[test]
+  fn test_overall_ratio_empty_is_fully_covered() {
+    assert_eq!(overall_ratio(&[]), 100.0);
+  }
+}
@@ -0,0 +1,194 @@
+use super::test_case::TestCase;
+use crate::lang::SgLang;
+
+use anyhow::Result;
+use ast_grep_config::{from_str, GlobalRules, RuleCollection, RuleConfig};
+use regex::Regex;
+
+use std::path::Path;
+
+/// A fenced code block extracted from a Markdown file, tagged with its
+/// info string (e.g. `sgtest id=no-console` or `valid`/`invalid`).
+struct FencedBlock {
+  info: String,
+  code: String,
+}
+
+fn fenced_blocks(markdown: &str) -> Vec<FencedBlock> {
+  let mut blocks = vec![];
+  let mut lines = markdown.lines();
+  while let Some(line) = lines.by_ref().next() {
+    let Some(info) = line.strip_prefix("```") else {
+      continue;
+    };
+    let mut code = String::new();
+    for line in lines.by_ref() {
+      if line.starts_with("```") {
+        break;
+      }
+      code.push_str(line);
+      code.push('\n');
+    }
+    blocks.push(FencedBlock {
+      info: info.trim().to_string(),
+      code,
+    });
+  }
+  blocks
+}
+
+fn sgtest_id(info: &str) -> Option<&str> {
+  let rest = info.strip_prefix("sgtest")?;
+  Regex::new(r#"id=(\S+)"#)
+    .unwrap()
+    .captures(rest)
+    .map(|c| c.get(1).unwrap().as_str())
+}
+
+/// Parse a `sgtest` yaml block's body into a standalone `RuleConfig`, so a
+/// Markdown file can define the rule it documents rather than only
+/// referencing one already loaded via `--config`. An `id:` already present
+/// in the yaml is kept as is; otherwise the `sgtest id=...` directive
+/// supplies it.
+///
+/// If the yaml's own `id:` disagrees with the `sgtest id=...` directive, the
+/// `TestCase` pushed for this block (keyed by the directive's id) would
+/// silently never find the rule we just parsed (keyed by its own id), so we
+/// reject the block instead of registering a rule nothing can reach.
+fn parse_inline_rule(id: &str, yaml: &str) -> Option<RuleConfig<SgLang>> {
+  if yaml.trim().is_empty() {
+    return None;
+  }
+  let yaml = if yaml.contains("id:") {
+    yaml.to_string()
+  } else {
+    format!("id: {id}\n{yaml}")
+  };
+  let globals = GlobalRules::default();
+  let inner = from_str(&yaml).ok()?;
+  let rule = RuleConfig::try_from(inner, &globals).ok()?;
+  if rule.id != id {
+    eprintln!(
+      "warning: sgtest block's `id: {}` does not match its `sgtest id={id}` directive; skipping",
+      rule.id
+    );
+    return None;
+  }
+  Some(rule)
+}
+
+/// Walk the fenced blocks of one Markdown file in order, pairing every
+/// `sgtest id=...` rule block with the `valid`/`invalid` blocks that follow
+/// it, up to the next `sgtest` block.
+fn test_cases_in_markdown(markdown: &str) -> (Vec<TestCase>, Vec<RuleConfig<SgLang>>) {
+  let blocks = fenced_blocks(markdown);
+  let mut cases = vec![];
+  let mut rules = vec![];
+  let mut current: Option<TestCase> = None;
+  for block in blocks {
+    if let Some(id) = sgtest_id(&block.info) {
+      if let Some(case) = current.take() {
+        cases.push(case);
+      }
+      if let Some(rule) = parse_inline_rule(id, &block.code) {
+        rules.push(rule);
+      }
+      current = Some(TestCase {
+        id: id.to_string(),
+        valid: vec![],
+        invalid: vec![],
+      });
+      continue;
+    }
+    let Some(case) = current.as_mut() else {
+      continue;
+    };
+    if block.info.contains("valid") && !block.info.contains("invalid") {
+      case.valid.push(block.code);
+    } else if block.info.contains("invalid") {
+      case.invalid.push(block.code);
+    }
+  }
+  if let Some(case) = current.take() {
+    cases.push(case);
+  }
+  (cases, rules)
+}
+
+/// Glob `**/*.md` under `doc_dir` and extract `TestCase`s documented via the
+/// `sgtest`/`valid`/`invalid` fenced-block convention, along with any rule
+/// that a `sgtest` block defines inline rather than merely referencing.
+pub fn find_doc_tests(doc_dir: &Path) -> Result<(Vec<TestCase>, RuleCollection<SgLang>)> {
+  let pattern = doc_dir.join("**/*.md");
+  let pattern = pattern.to_string_lossy();
+  let mut cases = vec![];
+  let mut rules = vec![];
+  for entry in glob::glob(&pattern)? {
+    let path = entry?;
+    let markdown = std::fs::read_to_string(&path)?;
+    let (file_cases, file_rules) = test_cases_in_markdown(&markdown);
+    cases.extend(file_cases);
+    rules.extend(file_rules);
+  }
+  let collection = RuleCollection::try_new(rules).map_err(|e| anyhow::anyhow!("{e}"))?;
+  Ok((cases, collection))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  const FENCE: &str = "```";
+
+  fn markdown() -> String {
+    [
+      "# no-console",
+      "",
+      &format!("{FENCE}sgtest id=no-console"),
+      "language: TypeScript",
+      "rule:",
+      "  pattern: console.log($$$)",
+      FENCE,
+      "",
+      &format!("{FENCE}valid"),
+      "foo()",
+      FENCE,
+      "",
+      &format!("{FENCE}invalid"),
+      "console.log(1)",
+      FENCE,
+      "",
+    ]
+    .join("\n")
+  }
+
+  #[test]
+  fn test_sgtest_id() {
+    assert_eq!(sgtest_id("sgtest id=no-console"), Some("no-console"));
+    assert_eq!(sgtest_id("valid"), None);
+  }
+
+  #[test]
+  fn test_parse_inline_rule_defaults_id_from_directive() {
+    let yaml = "language: TypeScript\nrule:\n  pattern: console.log($$$)\n";
+    let rule = parse_inline_rule("no-console", yaml).unwrap();
+    assert_eq!(rule.id, "no-console");
+  }
+
+  #[test]
+  fn test_parse_inline_rule_rejects_mismatched_id() {
+    let yaml = "id: other-id\nlanguage: TypeScript\nrule:\n  pattern: console.log($$$)\n";
+    assert!(parse_inline_rule("no-console", yaml).is_none());
+  }
+
+  #[test]
+  fn test_test_cases_in_markdown_pairs_rule_with_valid_invalid() {
+    let (cases, rules) = test_cases_in_markdown(&markdown());
+    assert_eq!(cases.len(), 1);
+    assert_eq!(cases[0].id, "no-console");
+    assert_eq!(cases[0].valid, vec!["foo()\n".to_string()]);
+    assert_eq!(cases[0].invalid, vec!["console.log(1)\n".to_string()]);
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0].id, "no-console");
+  }
+}
@@ -0,0 +1,150 @@
+use super::reporter::Reporter;
+use super::snapshot::SnapshotAction;
+use super::test_case::TestCase;
+use super::{CaseResult, CaseStatus};
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, to_writer, Value};
+
+use std::io::Write;
+
+/// Emits one JSON record per `CaseResult`, followed by a final totals object.
+/// Intended for consumption by external tooling rather than humans.
+pub struct JsonReporter<W: Write> {
+  pub output: W,
+}
+
+#[derive(Serialize)]
+struct StatusRecord {
+  kind: &'static str,
+  text: Option<String>,
+  expected_fix: Option<String>,
+  actual_fix: Option<String>,
+}
+
+fn status_record(status: &CaseStatus) -> StatusRecord {
+  match status {
+    CaseStatus::Validated => StatusRecord {
+      kind: "validated",
+      text: None,
+      expected_fix: None,
+      actual_fix: None,
+    },
+    CaseStatus::Reported => StatusRecord {
+      kind: "reported",
+      text: None,
+      expected_fix: None,
+      actual_fix: None,
+    },
+    CaseStatus::Noisy(text) => StatusRecord {
+      kind: "noisy",
+      text: Some(text.to_string()),
+      expected_fix: None,
+      actual_fix: None,
+    },
+    CaseStatus::Missing(text) => StatusRecord {
+      kind: "missing",
+      text: Some(text.to_string()),
+      expected_fix: None,
+      actual_fix: None,
+    },
+    CaseStatus::Wrong { actual, expected } => StatusRecord {
+      kind: "wrong",
+      text: Some(actual.source.to_string()),
+      expected_fix: Some(expected.clone()),
+      actual_fix: actual.fixed.clone(),
+    },
+  }
+}
+
+impl<W: Write> Reporter for JsonReporter<W> {
+  fn get_output(&mut self) -> &mut dyn Write {
+    &mut self.output
+  }
+
+  fn before_report(&mut self, _test_cases: &[TestCase]) -> Result<()> {
+    Ok(())
+  }
+
+  fn report_case_summary(&mut self, id: &str, cases: &[CaseStatus]) -> Result<()> {
+    let record: Value = json!({
+      "id": id,
+      "cases": cases.iter().map(status_record).collect::<Vec<_>>(),
+    });
+    to_writer(&mut self.output, &record)?;
+    writeln!(self.output)?;
+    Ok(())
+  }
+
+  fn after_report(&mut self, results: &[CaseResult]) -> Result<(bool, String)> {
+    let total: usize = results.iter().map(|r| r.cases.len()).sum();
+    let failed: usize = results
+      .iter()
+      .flat_map(|r| &r.cases)
+      .filter(|c| !matches!(c, CaseStatus::Validated | CaseStatus::Reported))
+      .count();
+    let passed = failed == 0;
+    let totals = json!({ "total": total, "failed": failed });
+    to_writer(&mut self.output, &totals)?;
+    writeln!(self.output)?;
+    let message = format!("{failed}/{total} test cases failed");
+    Ok((passed, message))
+  }
+
+  fn report_failed_cases(&mut self, _results: &[CaseResult]) -> Result<()> {
+    // failures are already part of the per-case records emitted above
+    Ok(())
+  }
+
+  fn collect_snapshot_action(&mut self) -> SnapshotAction {
+    SnapshotAction::NoAccept
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn lines(output: Vec<u8>) -> Vec<Value> {
+    String::from_utf8(output)
+      .unwrap()
+      .lines()
+      .map(|line| serde_json::from_str(line).unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn test_report_case_summary_emits_one_json_line_per_case() {
+    let mut reporter = JsonReporter { output: vec![] };
+    reporter
+      .report_case_summary("rule-a", &[CaseStatus::Validated, CaseStatus::Noisy("1")])
+      .unwrap();
+    let records = lines(reporter.output);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["id"], "rule-a");
+    assert_eq!(records[0]["cases"][0]["kind"], "validated");
+    assert_eq!(records[0]["cases"][1]["kind"], "noisy");
+    assert_eq!(records[0]["cases"][1]["text"], "1");
+  }
+
+  #[test]
+  fn test_after_report_totals_and_pass_fail() {
+    let mut reporter = JsonReporter { output: vec![] };
+    let results = vec![
+      CaseResult {
+        id: "rule-a",
+        cases: vec![CaseStatus::Validated],
+      },
+      CaseResult {
+        id: "rule-b",
+        cases: vec![CaseStatus::Noisy("1")],
+      },
+    ];
+    let (passed, _message) = reporter.after_report(&results).unwrap();
+    assert!(!passed);
+    let records = lines(reporter.output);
+    assert_eq!(records[0]["total"], 2);
+    assert_eq!(records[0]["failed"], 1);
+  }
+}
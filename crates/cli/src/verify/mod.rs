@@ -1,8 +1,14 @@
 mod case_result;
+mod coverage;
+mod doc_test;
 mod find_file;
+mod json_reporter;
 mod reporter;
+mod reporter_ext;
 mod snapshot;
+mod tap_reporter;
 mod test_case;
+mod watch;
 
 use crate::config::{find_rules, register_custom_language};
 use crate::error::ErrorContext;
@@ -10,7 +16,7 @@ use crate::lang::SgLang;
 use anyhow::{anyhow, Result};
 use ast_grep_config::RuleCollection;
 use ast_grep_core::{Node as SgNode, StrDoc};
-use clap::Args;
+use clap::{Args, ValueEnum};
 use regex::Regex;
 use serde_yaml::to_string;
 
@@ -22,8 +28,11 @@ use std::thread;
 
 pub use case_result::{CaseResult, CaseStatus};
 use find_file::{find_tests, read_test_files, TestHarness};
+use json_reporter::JsonReporter;
 use reporter::{DefaultReporter, InteractiveReporter, Reporter};
+use reporter_ext::ReporterExt;
 use snapshot::{SnapshotAction, SnapshotCollection, TestSnapshots};
+use tap_reporter::TapReporter;
 pub use test_case::TestCase;
 use test_case::{verify_test_case, verify_test_case_with_snapshots};
 
@@ -55,10 +64,13 @@ where
   })
 }
 
-fn run_test_rule_impl<R: Reporter + Send>(arg: TestArg, reporter: R) -> Result<()> {
+fn run_test_rule_impl<R: Reporter + ReporterExt + Send>(arg: TestArg, reporter: R) -> Result<()> {
+  let show_coverage = arg.coverage;
+  let coverage_threshold = arg.coverage_threshold;
+  let doc_dir = arg.doc_dir.clone();
   let collections = &find_rules(arg.config.clone(), None)?;
   let TestHarness {
-    test_cases,
+    mut test_cases,
     snapshots,
     path_map,
   } = if let Some(test_dirname) = arg.test_dir {
@@ -73,14 +85,31 @@ fn run_test_rule_impl<R: Reporter + Send>(arg: TestArg, reporter: R) -> Result<(
   } else {
     find_tests(arg.config, arg.filter.as_ref())?
   };
+  let doc_rules = if let Some(doc_dir) = doc_dir {
+    let (doc_cases, doc_rules) = doc_test::find_doc_tests(&doc_dir)?;
+    test_cases.extend(doc_cases);
+    Some(doc_rules)
+  } else {
+    None
+  };
+  let doc_rules = doc_rules.as_ref();
   let snapshots = (!arg.skip_snapshot_tests).then_some(snapshots);
+  // split off cases whose rule id never resolves so they don't inflate a
+  // TAP plan count or interleave raw text into a JSON/TAP stream
+  let (test_cases, unresolved): (Vec<_>, Vec<_>) = test_cases
+    .into_iter()
+    .partition(|case| resolve_rule(&case.id, collections, doc_rules).is_some());
   let reporter = &Arc::new(Mutex::new(reporter));
   {
-    reporter.lock().unwrap().before_report(&test_cases)?;
+    let mut reporter = reporter.lock().unwrap();
+    reporter.before_report(&test_cases)?;
+    for case in &unresolved {
+      reporter.report_rule_not_found(&case.id)?;
+    }
   }
 
   let check_one_case = |case| {
-    let result = verify_test_case_simple(case, collections, snapshots.as_ref());
+    let result = verify_test_case_simple(case, collections, doc_rules, snapshots.as_ref());
     let mut reporter = reporter.lock().unwrap();
     if let Some(result) = result {
       reporter
@@ -88,13 +117,28 @@ fn run_test_rule_impl<R: Reporter + Send>(arg: TestArg, reporter: R) -> Result<(
         .unwrap();
       Some(result)
     } else {
-      let output = reporter.get_output();
-      writeln!(output, "Configuration not found! {}", case.id).unwrap();
+      reporter.report_rule_not_found(&case.id).unwrap();
       None
     }
   };
   let results = parallel_collect(&test_cases, check_one_case);
   let mut reporter = reporter.lock().unwrap();
+
+  let rule_coverage = (show_coverage || coverage_threshold.is_some())
+    .then(|| coverage::compute_coverage(collections, &test_cases));
+  if let Some(rule_coverage) = &rule_coverage {
+    if show_coverage {
+      coverage::print_coverage_table(reporter.get_output(), rule_coverage)?;
+    }
+    if let Some(threshold) = coverage_threshold {
+      let ratio = coverage::overall_ratio(rule_coverage);
+      if ratio < threshold {
+        let message = format!("rule test coverage {ratio:.1}% is below threshold {threshold:.1}%");
+        return Err(anyhow!(ErrorContext::TestFail(message)));
+      }
+    }
+  }
+
   let (passed, message) = reporter.after_report(&results)?;
   if passed {
     writeln!(reporter.get_output(), "{message}",)?;
@@ -137,12 +181,23 @@ fn write_merged_to_disk(
   Ok(())
 }
 
+fn resolve_rule<'a>(
+  id: &str,
+  rules: &'a RuleCollection<SgLang>,
+  extra: Option<&'a RuleCollection<SgLang>>,
+) -> Option<&'a ast_grep_config::RuleConfig<SgLang>> {
+  rules
+    .get_rule(id)
+    .or_else(|| extra.and_then(|extra| extra.get_rule(id)))
+}
+
 fn verify_test_case_simple<'a>(
   test_case: &'a TestCase,
   rules: &RuleCollection<SgLang>,
+  extra: Option<&RuleCollection<SgLang>>,
   snapshots: Option<&SnapshotCollection>,
 ) -> Option<CaseResult<'a>> {
-  let rule_config = rules.get_rule(&test_case.id)?;
+  let rule_config = resolve_rule(&test_case.id, rules, extra)?;
   let test_case = if let Some(snapshots) = snapshots {
     let snaps = snapshots.get(&test_case.id);
     verify_test_case_with_snapshots(test_case, rule_config, snaps)
@@ -188,23 +243,71 @@ pub struct TestArg {
   /// Only run rule test cases that matches REGEX.
   #[clap(short, long, value_name = "REGEX")]
   filter: Option<Regex>,
+  /// Watch rule and test files, rerunning only the affected cases on change.
+  #[clap(short, long)]
+  watch: bool,
+  /// Output format for machine consumption, e.g. by CI harnesses.
+  /// Not supported in --watch mode.
+  #[clap(long, conflicts_with = "watch")]
+  format: Option<OutputFormat>,
+  /// Print a rule-level test coverage summary after the run: which rules
+  /// have at least one `valid`/`invalid` case at all. This does NOT measure
+  /// which `all`/`any`/`not` branch of a rule a case actually exercised.
+  /// Not supported in --watch mode.
+  #[clap(long, conflicts_with = "watch")]
+  coverage: bool,
+  /// Fail the run if the ratio of rules with a test case (see --coverage)
+  /// falls below PERCENT. Not supported in --watch mode.
+  #[clap(long, value_name = "PERCENT", conflicts_with = "watch")]
+  coverage_threshold: Option<f64>,
+  /// Glob Markdown files under PATH for `sgtest`/`valid`/`invalid` fenced
+  /// examples and run them as test cases, keeping documentation honest.
+  /// Not supported in --watch mode.
+  #[clap(long, value_name = "PATH", conflicts_with = "watch")]
+  doc_dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+  Json,
+  Tap,
 }
 
 pub fn run_test_rule(arg: TestArg) -> Result<()> {
   register_custom_language(arg.config.clone());
-  if arg.interactive {
-    let reporter = InteractiveReporter {
-      output: std::io::stdout(),
-      accepted_snapshots: HashMap::new(),
-      should_accept_all: false,
-    };
-    run_test_rule_impl(arg, reporter)
-  } else {
+  if arg.watch {
     let reporter = DefaultReporter {
       output: std::io::stdout(),
       update_all: arg.update_all,
     };
-    run_test_rule_impl(arg, reporter)
+    return watch::run_test_rule_watch(arg, reporter);
+  }
+  match arg.format {
+    Some(OutputFormat::Json) => {
+      let reporter = JsonReporter {
+        output: std::io::stdout(),
+      };
+      run_test_rule_impl(arg, reporter)
+    }
+    Some(OutputFormat::Tap) => {
+      let reporter = TapReporter::new(std::io::stdout());
+      run_test_rule_impl(arg, reporter)
+    }
+    None if arg.interactive => {
+      let reporter = InteractiveReporter {
+        output: std::io::stdout(),
+        accepted_snapshots: HashMap::new(),
+        should_accept_all: false,
+      };
+      run_test_rule_impl(arg, reporter)
+    }
+    None => {
+      let reporter = DefaultReporter {
+        output: std::io::stdout(),
+        update_all: arg.update_all,
+      };
+      run_test_rule_impl(arg, reporter)
+    }
   }
 }
 
@@ -271,7 +374,7 @@ rule:
   fn test_validated() {
     let rule = never_report_rule();
     let case = valid_case();
-    let ret = verify_test_case_simple(&case, &rule, None);
+    let ret = verify_test_case_simple(&case, &rule, None, None);
     assert_eq!(ret, test_case_result(CaseStatus::Validated),);
   }
 
@@ -279,21 +382,21 @@ rule:
   fn test_reported() {
     let case = invalid_case();
     let rule = always_report_rule();
-    let ret = verify_test_case_simple(&case, &rule, None);
+    let ret = verify_test_case_simple(&case, &rule, None, None);
     assert_eq!(ret, test_case_result(CaseStatus::Reported),);
   }
   #[test]
   fn test_noisy() {
     let case = valid_case();
     let rule = always_report_rule();
-    let ret = verify_test_case_simple(&case, &rule, None);
+    let ret = verify_test_case_simple(&case, &rule, None, None);
     assert_eq!(ret, test_case_result(CaseStatus::Noisy("123")),);
   }
   #[test]
   fn test_missing() {
     let case = invalid_case();
     let rule = never_report_rule();
-    let ret = verify_test_case_simple(&case, &rule, None);
+    let ret = verify_test_case_simple(&case, &rule, None, None);
     assert_eq!(ret, test_case_result(CaseStatus::Missing("123")),);
   }
 
@@ -305,7 +408,7 @@ rule:
       invalid: vec![],
     };
     let rule = never_report_rule();
-    let ret = verify_test_case_simple(&case, &rule, None);
+    let ret = verify_test_case_simple(&case, &rule, None, None);
     assert!(ret.is_none());
   }
 
@@ -324,6 +427,11 @@ rule:
       test_dir: None,
       update_all: false,
       filter: None,
+      watch: false,
+      format: None,
+      coverage: false,
+      coverage_threshold: None,
+      doc_dir: None,
     };
     assert!(run_test_rule_impl(arg, reporter).is_err());
   }
@@ -351,7 +459,7 @@ fix: 'log($B)'";
       invalid: vec!["console.log(123)".to_string()],
     };
     let snapshots = SnapshotCollection::new();
-    let mut ret = verify_test_case_simple(&case, &rule, Some(&snapshots)).unwrap();
+    let mut ret = verify_test_case_simple(&case, &rule, None, Some(&snapshots)).unwrap();
     let case = ret.cases.pop().unwrap();
     match case {
       CaseStatus::Wrong { actual, .. } => {
@@ -0,0 +1,39 @@
+use super::reporter::{DefaultReporter, InteractiveReporter, Reporter};
+use super::json_reporter::JsonReporter;
+use super::tap_reporter::TapReporter;
+
+use anyhow::Result;
+
+use std::io::Write;
+
+/// Extends `Reporter` with a hook for a test case whose rule id doesn't
+/// resolve in the loaded `RuleCollection`, so each reporter can surface it in
+/// its own format instead of a raw string landing in the middle of a
+/// machine-parseable stream.
+pub trait ReporterExt: Reporter {
+  fn report_rule_not_found(&mut self, id: &str) -> Result<()> {
+    writeln!(self.get_output(), "Configuration not found! {id}")?;
+    Ok(())
+  }
+}
+
+impl<W: Write> ReporterExt for DefaultReporter<W> {}
+impl<W: Write> ReporterExt for InteractiveReporter<W> {}
+
+impl<W: Write> ReporterExt for JsonReporter<W> {
+  fn report_rule_not_found(&mut self, id: &str) -> Result<()> {
+    let record = serde_json::json!({ "id": id, "kind": "ruleNotFound" });
+    serde_json::to_writer(&mut self.output, &record)?;
+    writeln!(self.output)?;
+    Ok(())
+  }
+}
+
+impl<W: Write> ReporterExt for TapReporter<W> {
+  fn report_rule_not_found(&mut self, id: &str) -> Result<()> {
+    // not counted in the `1..N` plan emitted by `before_report`, since it
+    // never produces an ok/not-ok line; surface it as a TAP comment instead
+    writeln!(self.output, "# {id}: configuration not found")?;
+    Ok(())
+  }
+}
@@ -0,0 +1,138 @@
+use super::reporter::Reporter;
+use super::snapshot::SnapshotAction;
+use super::test_case::TestCase;
+use super::{CaseResult, CaseStatus};
+
+use anyhow::Result;
+
+use std::io::Write;
+
+/// Emits a TAP version 13 stream so rule test suites plug into CI harnesses
+/// that consume the Test Anything Protocol.
+pub struct TapReporter<W: Write> {
+  pub output: W,
+  count: usize,
+}
+
+impl<W: Write> TapReporter<W> {
+  pub fn new(output: W) -> Self {
+    Self { output, count: 0 }
+  }
+}
+
+fn snippet(status: &CaseStatus) -> Option<String> {
+  match status {
+    CaseStatus::Noisy(text) | CaseStatus::Missing(text) => Some(text.to_string()),
+    CaseStatus::Wrong { actual, .. } => Some(actual.source.to_string()),
+    CaseStatus::Validated | CaseStatus::Reported => None,
+  }
+}
+
+fn ok(status: &CaseStatus) -> bool {
+  matches!(status, CaseStatus::Validated | CaseStatus::Reported)
+}
+
+impl<W: Write> Reporter for TapReporter<W> {
+  fn get_output(&mut self) -> &mut dyn Write {
+    &mut self.output
+  }
+
+  fn before_report(&mut self, test_cases: &[TestCase]) -> Result<()> {
+    let plan: usize = test_cases
+      .iter()
+      .map(|c| c.valid.len() + c.invalid.len())
+      .sum();
+    writeln!(self.output, "TAP version 13")?;
+    writeln!(self.output, "1..{plan}")?;
+    Ok(())
+  }
+
+  fn report_case_summary(&mut self, id: &str, cases: &[CaseStatus]) -> Result<()> {
+    for status in cases {
+      self.count += 1;
+      if ok(status) {
+        writeln!(self.output, "ok {} - {id}", self.count)?;
+      } else {
+        let detail = snippet(status).unwrap_or_default();
+        writeln!(self.output, "not ok {} - {id} # {detail}", self.count)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn after_report(&mut self, results: &[CaseResult]) -> Result<(bool, String)> {
+    let failed = results
+      .iter()
+      .flat_map(|r| &r.cases)
+      .filter(|c| !ok(c))
+      .count();
+    let passed = failed == 0;
+    let message = format!("{failed} not ok out of {}", self.count);
+    Ok((passed, message))
+  }
+
+  fn report_failed_cases(&mut self, _results: &[CaseResult]) -> Result<()> {
+    // `not ok` lines above already carry the failure detail
+    Ok(())
+  }
+
+  fn collect_snapshot_action(&mut self) -> SnapshotAction {
+    SnapshotAction::NoAccept
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn output(reporter: TapReporter<Vec<u8>>) -> String {
+    String::from_utf8(reporter.output).unwrap()
+  }
+
+  #[test]
+  fn test_before_report_plan_matches_valid_plus_invalid_counts() {
+    let mut reporter = TapReporter::new(vec![]);
+    let cases = vec![
+      TestCase {
+        id: "rule-a".into(),
+        valid: vec!["1".into()],
+        invalid: vec!["2".into(), "3".into()],
+      },
+      TestCase {
+        id: "rule-b".into(),
+        valid: vec![],
+        invalid: vec![],
+      },
+    ];
+    reporter.before_report(&cases).unwrap();
+    let out = output(reporter);
+    assert!(out.contains("TAP version 13"));
+    assert!(out.contains("1..3"));
+  }
+
+  #[test]
+  fn test_report_case_summary_emits_ok_and_not_ok() {
+    let mut reporter = TapReporter::new(vec![]);
+    reporter
+      .report_case_summary("rule-a", &[CaseStatus::Validated, CaseStatus::Noisy("snippet")])
+      .unwrap();
+    let out = output(reporter);
+    assert!(out.contains("ok 1 - rule-a"));
+    assert!(out.contains("not ok 2 - rule-a # snippet"));
+  }
+
+  #[test]
+  fn test_after_report_counts_not_ok() {
+    let mut reporter = TapReporter::new(vec![]);
+    let results = vec![CaseResult {
+      id: "rule-a",
+      cases: vec![CaseStatus::Validated, CaseStatus::Missing("x")],
+    }];
+    reporter
+      .report_case_summary("rule-a", &results[0].cases)
+      .unwrap();
+    let (passed, message) = reporter.after_report(&results).unwrap();
+    assert!(!passed);
+    assert_eq!(message, "1 not ok out of 2");
+  }
+}
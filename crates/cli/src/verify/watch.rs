@@ -0,0 +1,344 @@
+use super::find_file::{find_tests, read_test_files, TestHarness};
+use super::reporter::Reporter;
+use super::reporter_ext::ReporterExt;
+use super::snapshot::SnapshotCollection;
+use super::test_case::TestCase;
+use super::{apply_snapshot_action, parallel_collect, verify_test_case_simple, TestArg};
+use crate::config::find_rules;
+
+use anyhow::Result;
+use notify::{RecursiveMode, Watcher};
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// coalesce a burst of editor saves into a single rerun
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+struct Loaded {
+  test_cases: Vec<TestCase>,
+  snapshots: Option<SnapshotCollection>,
+  path_map: HashMap<String, PathBuf>,
+}
+
+fn load(arg: &TestArg) -> Result<(ast_grep_config::RuleCollection<crate::lang::SgLang>, Loaded)> {
+  let collections = find_rules(arg.config.clone(), None)?;
+  let TestHarness {
+    test_cases,
+    snapshots,
+    path_map,
+  } = if let Some(test_dirname) = &arg.test_dir {
+    let base_dir = std::env::current_dir()?;
+    let snapshot_dirname = arg.snapshot_dir.as_deref();
+    read_test_files(&base_dir, test_dirname, snapshot_dirname, arg.filter.as_ref())?
+  } else {
+    find_tests(arg.config.clone(), arg.filter.as_ref())?
+  };
+  let snapshots = (!arg.skip_snapshot_tests).then_some(snapshots);
+  Ok((
+    collections,
+    Loaded {
+      test_cases,
+      snapshots,
+      path_map,
+    },
+  ))
+}
+
+/// a changed path, classified by what it should trigger
+enum Change {
+  /// the root config changed; reload rules and test cases from scratch
+  FullReload,
+  /// these rule YAMLs changed; reload rules (picking up the edits) and
+  /// rerun only the `TestCase`s for the ids they define
+  Rules(HashSet<String>),
+  /// these rule ids' test/snapshot files changed; rerun their `TestCase`s
+  /// without reloading the rules themselves
+  Tests(HashSet<String>),
+}
+
+/// watched path -> the rule/test ids it reruns, or a full reload
+struct WatchMap {
+  /// the root config file/dir; any change under it triggers `find_rules` to reload
+  config: PathBuf,
+  /// test/snapshot dir -> rule ids sharing that dir (the normal layout has
+  /// several ids share one directory, so this must accumulate, not overwrite)
+  by_dir: HashMap<PathBuf, HashSet<String>>,
+  /// a rule's own YAML file -> rule ids defined in it (usually one, but a
+  /// single file may define several rules sharing it)
+  by_rule_file: HashMap<PathBuf, HashSet<String>>,
+}
+
+impl WatchMap {
+  fn build(
+    arg: &TestArg,
+    collections: &ast_grep_config::RuleCollection<crate::lang::SgLang>,
+    loaded: &Loaded,
+  ) -> Self {
+    let config = arg
+      .config
+      .clone()
+      .unwrap_or_else(|| PathBuf::from("sgconfig.yml"));
+    let mut by_dir: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+    for (id, path) in &loaded.path_map {
+      by_dir.entry(path.clone()).or_default().insert(id.clone());
+    }
+    let mut by_rule_file: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+    for id in collections.ids() {
+      if let Some(rule) = collections.get_rule(id) {
+        by_rule_file
+          .entry(rule.path.clone())
+          .or_default()
+          .insert(id.to_string());
+      }
+    }
+    WatchMap {
+      config,
+      by_dir,
+      by_rule_file,
+    }
+  }
+
+  fn watch_paths(&self) -> impl Iterator<Item = &PathBuf> {
+    std::iter::once(&self.config)
+      .chain(self.by_dir.keys())
+      .chain(self.by_rule_file.keys())
+  }
+
+  /// classify a changed path as a full reload, a rule-file change, or a
+  /// test/snapshot-file change
+  fn classify(&self, path: &std::path::Path) -> Change {
+    if path.starts_with(&self.config) {
+      return Change::FullReload;
+    }
+    let rule_ids: HashSet<String> = self
+      .by_rule_file
+      .iter()
+      .filter(|(file, _)| path == file.as_path())
+      .flat_map(|(_, ids)| ids.iter().cloned())
+      .collect();
+    if !rule_ids.is_empty() {
+      return Change::Rules(rule_ids);
+    }
+    let test_ids: HashSet<String> = self
+      .by_dir
+      .iter()
+      .filter(|(dir, _)| path.starts_with(dir))
+      .flat_map(|(_, ids)| ids.iter().cloned())
+      .collect();
+    Change::Tests(test_ids)
+  }
+}
+
+fn clear_terminal() {
+  print!("\x1B[2J\x1B[1;1H");
+}
+
+fn run_cycle<R: Reporter + ReporterExt + Send>(
+  collections: &ast_grep_config::RuleCollection<crate::lang::SgLang>,
+  loaded: &Loaded,
+  only_ids: Option<&HashSet<String>>,
+  reporter: &Arc<Mutex<R>>,
+) -> Result<()> {
+  clear_terminal();
+  {
+    reporter.lock().unwrap().before_report(&loaded.test_cases)?;
+  }
+  let check_one_case = |case: &TestCase| {
+    if only_ids.is_some_and(|ids| !ids.contains(&case.id)) {
+      return None;
+    }
+    let result = verify_test_case_simple(case, collections, None, loaded.snapshots.as_ref());
+    let mut reporter = reporter.lock().unwrap();
+    if let Some(result) = result {
+      reporter
+        .report_case_summary(&case.id, &result.cases)
+        .unwrap();
+      Some(result)
+    } else {
+      reporter.report_rule_not_found(&case.id).unwrap();
+      None
+    }
+  };
+  let results = parallel_collect(&loaded.test_cases, check_one_case);
+  let mut reporter = reporter.lock().unwrap();
+  let (passed, message) = reporter.after_report(&results)?;
+  if passed {
+    writeln!(reporter.get_output(), "{message}")?;
+  } else {
+    reporter.report_failed_cases(&results)?;
+    let action = reporter.collect_snapshot_action();
+    apply_snapshot_action(action, &results, loaded.snapshots.clone(), loaded.path_map.clone())?;
+  }
+  writeln!(reporter.get_output(), "\nwatching for changes... (ctrl-c to exit)")?;
+  Ok(())
+}
+
+fn watch_all<'a>(watcher: &mut notify::RecommendedWatcher, paths: impl Iterator<Item = &'a PathBuf>) {
+  for path in paths {
+    let mode = if path.is_dir() {
+      RecursiveMode::Recursive
+    } else {
+      RecursiveMode::NonRecursive
+    };
+    // the path may not exist yet (e.g. a test dir created later); skip it silently
+    let _ = watcher.watch(path, mode);
+  }
+}
+
+pub fn run_test_rule_watch<R: Reporter + ReporterExt + Send>(arg: TestArg, reporter: R) -> Result<()> {
+  let reporter = Arc::new(Mutex::new(reporter));
+  let (mut collections, mut loaded) = load(&arg)?;
+  let mut watch_map = WatchMap::build(&arg, &collections, &loaded);
+  run_cycle(&collections, &loaded, None, &reporter)?;
+
+  let (tx, rx) = channel();
+  let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+    if let Ok(event) = res {
+      let _ = tx.send(event);
+    }
+  })?;
+  watch_all(&mut watcher, watch_map.watch_paths());
+
+  loop {
+    let Ok(first) = rx.recv() else {
+      return Ok(());
+    };
+    let mut paths = first.paths;
+    loop {
+      match rx.recv_timeout(DEBOUNCE) {
+        Ok(event) => paths.extend(event.paths),
+        Err(RecvTimeoutError::Timeout) => break,
+        Err(RecvTimeoutError::Disconnected) => break,
+      }
+    }
+    let changes: Vec<Change> = paths.iter().map(|p| watch_map.classify(p)).collect();
+    let full_reload = changes.iter().any(|c| matches!(c, Change::FullReload));
+    let rule_ids: HashSet<String> = changes
+      .iter()
+      .filter_map(|c| match c {
+        Change::Rules(ids) => Some(ids.iter().cloned()),
+        _ => None,
+      })
+      .flatten()
+      .collect();
+    let test_ids: HashSet<String> = changes
+      .iter()
+      .filter_map(|c| match c {
+        Change::Tests(ids) => Some(ids.iter().cloned()),
+        _ => None,
+      })
+      .flatten()
+      .collect();
+
+    if full_reload {
+      let (new_collections, new_loaded) = load(&arg)?;
+      collections = new_collections;
+      loaded = new_loaded;
+      watch_map = WatchMap::build(&arg, &collections, &loaded);
+      watch_all(&mut watcher, watch_map.watch_paths());
+      run_cycle(&collections, &loaded, None, &reporter)?;
+    } else if !rule_ids.is_empty() {
+      // a rule's own YAML changed: reload the rule definitions so the edit
+      // takes effect, but only rerun the ids the edited file(s) define
+      let (new_collections, new_loaded) = load(&arg)?;
+      collections = new_collections;
+      loaded = new_loaded;
+      watch_map = WatchMap::build(&arg, &collections, &loaded);
+      watch_all(&mut watcher, watch_map.watch_paths());
+      let affected: HashSet<String> = rule_ids.into_iter().chain(test_ids).collect();
+      run_cycle(&collections, &loaded, Some(&affected), &reporter)?;
+    } else if !test_ids.is_empty() {
+      // re-read the test/snapshot files so the edit takes effect; the rule
+      // definitions already loaded are kept
+      let (_, new_loaded) = load(&arg)?;
+      loaded = new_loaded;
+      run_cycle(&collections, &loaded, Some(&test_ids), &reporter)?;
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::path::Path;
+
+  fn watch_map(by_rule_file: &[(&str, &[&str])], by_dir: &[(&str, &[&str])]) -> WatchMap {
+    let ids = |names: &[&str]| names.iter().map(|s| s.to_string()).collect();
+    WatchMap {
+      config: PathBuf::from("sgconfig.yml"),
+      by_dir: by_dir
+        .iter()
+        .map(|(dir, names)| (PathBuf::from(dir), ids(names)))
+        .collect(),
+      by_rule_file: by_rule_file
+        .iter()
+        .map(|(file, names)| (PathBuf::from(file), ids(names)))
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn test_classify_config_change_is_full_reload() {
+    let watch_map = watch_map(&[("rules/a.yml", &["rule-a"])], &[]);
+    let change = watch_map.classify(Path::new("sgconfig.yml"));
+    assert!(matches!(change, Change::FullReload));
+  }
+
+  #[test]
+  fn test_classify_rule_file_change_returns_only_its_own_ids() {
+    let watch_map = watch_map(
+      &[("rules/a.yml", &["rule-a"]), ("rules/b.yml", &["rule-b"])],
+      &[],
+    );
+    let change = watch_map.classify(Path::new("rules/a.yml"));
+    match change {
+      Change::Rules(ids) => assert_eq!(ids, HashSet::from(["rule-a".to_string()])),
+      _ => panic!("expected Change::Rules"),
+    }
+  }
+
+  #[test]
+  fn test_classify_shared_rule_file_accumulates_all_ids() {
+    let watch_map = watch_map(&[("rules/shared.yml", &["rule-a", "rule-b"])], &[]);
+    let change = watch_map.classify(Path::new("rules/shared.yml"));
+    match change {
+      Change::Rules(ids) => {
+        assert_eq!(
+          ids,
+          HashSet::from(["rule-a".to_string(), "rule-b".to_string()])
+        );
+      }
+      _ => panic!("expected Change::Rules"),
+    }
+  }
+
+  #[test]
+  fn test_classify_shared_test_dir_accumulates_all_ids() {
+    let watch_map = watch_map(&[], &[("tests/shared", &["rule-a", "rule-b"])]);
+    let change = watch_map.classify(Path::new("tests/shared/case.yml"));
+    match change {
+      Change::Tests(ids) => {
+        assert_eq!(
+          ids,
+          HashSet::from(["rule-a".to_string(), "rule-b".to_string()])
+        );
+      }
+      _ => panic!("expected Change::Tests"),
+    }
+  }
+
+  #[test]
+  fn test_classify_unrelated_path_is_empty_test_change() {
+    let watch_map = watch_map(&[("rules/a.yml", &["rule-a"])], &[]);
+    let change = watch_map.classify(Path::new("some/other/path"));
+    match change {
+      Change::Tests(ids) => assert!(ids.is_empty()),
+      _ => panic!("expected Change::Tests"),
+    }
+  }
+}
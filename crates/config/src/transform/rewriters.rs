@@ -1,22 +1,77 @@
 use super::Ctx;
 use crate::rule_core::RuleCore;
 
-use ast_grep_core::meta_var::MetaVariable;
+use ast_grep_core::meta_var::{MetaVarEnv, MetaVariable};
 use ast_grep_core::source::{Content, Edit};
 use ast_grep_core::{Doc, Language, Matcher, Node};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use std::cell::RefCell;
+use std::fmt;
+
 #[derive(Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Rewriters {
   source: String,
   rewrites: Vec<String>,
-  // do we need this?
-  // sort_by: Option<String>,
+  sort_by: Option<String>,
   join_by: Option<String>,
 }
 
+#[derive(Debug)]
+pub enum RewriteError {
+  /// a rewriter rule matched a node inside a subtree it is already
+  /// rewriting, directly or through a cycle of other rewriters
+  Recursive(String),
+  /// two produced edits overlap, which would corrupt offsets if applied
+  Overlapping,
+}
+
+impl fmt::Display for RewriteError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      RewriteError::Recursive(id) => {
+        write!(f, "rewriter `{id}` re-entered itself recursively")
+      }
+      RewriteError::Overlapping => write!(f, "rewriter produced overlapping edits"),
+    }
+  }
+}
+
+impl std::error::Error for RewriteError {}
+
+thread_local! {
+  /// ids of rewriters whose matched subtree is currently being descended
+  /// into, so a rewriter that matches again inside its own match (directly,
+  /// or via a cycle of other rewriters) is rejected instead of recursing
+  /// forever
+  static ACTIVE_REWRITERS: RefCell<Vec<String>> = RefCell::new(Vec::new());
+}
+
+/// RAII guard marking `id` active for as long as we descend into the
+/// subtree of the node it just matched
+struct ActiveGuard;
+
+impl ActiveGuard {
+  fn enter(id: &str) -> Result<Self, RewriteError> {
+    let already_active = ACTIVE_REWRITERS.with(|a| a.borrow().iter().any(|s| s == id));
+    if already_active {
+      return Err(RewriteError::Recursive(id.to_string()));
+    }
+    ACTIVE_REWRITERS.with(|a| a.borrow_mut().push(id.to_string()));
+    Ok(ActiveGuard)
+  }
+}
+
+impl Drop for ActiveGuard {
+  fn drop(&mut self) {
+    ACTIVE_REWRITERS.with(|a| {
+      a.borrow_mut().pop();
+    });
+  }
+}
+
 fn get_nodes_from_env<'b, D: Doc>(var: &MetaVariable, ctx: &Ctx<'_, 'b, D>) -> Vec<Node<'b, D>> {
   match var {
     MetaVariable::MultiCapture(n) => ctx.env.get_multiple_matches(n),
@@ -31,83 +86,138 @@ fn get_nodes_from_env<'b, D: Doc>(var: &MetaVariable, ctx: &Ctx<'_, 'b, D>) -> V
   }
 }
 
+/// resolve a `sort_by` key (a metavariable like `$N`) against the env bound
+/// by a single match, to get the text to order that match's edit by
+fn sort_key<D: Doc>(env: &MetaVarEnv<D>, key: &str) -> Option<String> {
+  let name = key.trim_start_matches('$');
+  env.get_match(name).map(|n| n.text().to_string())
+}
+
+/// an `Edit` together with the `sort_by` key text resolved at the time it was
+/// produced, since the env it came from doesn't outlive the match
+struct KeyedEdit<D: Doc> {
+  key: Option<String>,
+  edit: Edit<D::Source>,
+}
+
 impl Rewriters {
-  pub(super) fn compute<D: Doc>(&self, ctx: &mut Ctx<D>) -> Option<String> {
+  pub(super) fn compute<D: Doc>(&self, ctx: &mut Ctx<D>) -> Result<Option<String>, RewriteError> {
     let source = ctx.lang.pre_process_pattern(&self.source);
-    let var = ctx.lang.extract_meta_var(&source)?;
+    let Some(var) = ctx.lang.extract_meta_var(&source) else {
+      return Ok(None);
+    };
     let nodes = get_nodes_from_env(&var, ctx);
     if nodes.is_empty() {
-      return None;
+      return Ok(None);
     }
     let start = nodes[0].range().start;
-    let bytes = ctx.env.get_var_bytes(&var)?;
-    let rules: Vec<_> = self
+    let Some(bytes) = ctx.env.get_var_bytes(&var) else {
+      return Ok(None);
+    };
+    let rules: Vec<(&str, &RuleCore<D::Lang>)> = self
       .rewrites
       .iter()
-      .filter_map(|id| ctx.rewriters.get(id))
+      .filter_map(|id| ctx.rewriters.get(id).map(|rule| (id.as_str(), rule)))
       .collect();
-    let edits = find_and_make_edits(nodes, &rules);
+    let parent_env = ctx.env.clone();
+    let mut edits = find_and_make_edits(nodes, &rules, &parent_env, self.sort_by.as_deref())?;
     let rewritten = if let Some(joiner) = &self.join_by {
+      // joined fragments don't need to preserve their original position in
+      // the source, so `sort_by` is free to reorder them by value
+      if self.sort_by.is_some() {
+        edits.sort_by(|a, b| a.key.cmp(&b.key));
+      } else {
+        edits.sort_by_key(|e| e.edit.position);
+      }
       let mut ret = vec![];
       let mut edits = edits.into_iter();
       if let Some(first) = edits.next() {
-        ret.extend(first.inserted_text);
+        ret.extend(first.edit.inserted_text);
         let joiner = D::Source::decode_str(joiner);
         for edit in edits {
           ret.extend_from_slice(&joiner);
-          ret.extend(edit.inserted_text);
+          ret.extend(edit.edit.inserted_text);
         }
         ret
       } else {
         ret
       }
     } else {
-      make_edit::<D>(bytes, edits, start)
+      // splicing back into the original source must follow position order
+      // regardless of `sort_by`, or `make_edit` below would see offsets go
+      // backwards and reject it as overlapping
+      edits.sort_by_key(|e| e.edit.position);
+      let edits = edits.into_iter().map(|k| k.edit).collect();
+      make_edit::<D>(bytes, edits, start)?
     };
-    Some(D::Source::encode_bytes(&rewritten).to_string())
+    Ok(Some(D::Source::encode_bytes(&rewritten).to_string()))
   }
 }
 
 type Bytes<D> = [<<D as Doc>::Source as Content>::Underlying];
+
 fn find_and_make_edits<D: Doc>(
   nodes: Vec<Node<D>>,
-  rules: &[&RuleCore<D::Lang>],
-) -> Vec<Edit<D::Source>> {
-  nodes
-    .into_iter()
-    .flat_map(|n| replace_one(n, rules))
-    .collect()
+  rules: &[(&str, &RuleCore<D::Lang>)],
+  parent_env: &MetaVarEnv<'_, D>,
+  sort_by: Option<&str>,
+) -> Result<Vec<KeyedEdit<D>>, RewriteError> {
+  let mut edits = vec![];
+  for node in nodes {
+    replace_recursive(node, rules, parent_env, sort_by, &mut edits)?;
+  }
+  Ok(edits)
 }
 
-fn replace_one<D: Doc>(node: Node<D>, rules: &[&RuleCore<D::Lang>]) -> Vec<Edit<D::Source>> {
-  let mut edits = vec![];
-  for child in node.dfs() {
-    for rule in rules {
-      // TODO inherit deserialize_env and meta_var_env
-      if let Some(nm) = rule.match_node(child.clone()) {
-        edits.push(nm.make_edit(rule, rule.fixer.as_ref().expect("TODO")));
-      }
-    }
+/// match `node` (and, recursively, its descendants) against `rules`. Every
+/// id that matches `node` itself guards its own subtree for the duration of
+/// the recursive descent below, so a rule that matches again inside its own
+/// match is a recursion error rather than an infinite/duplicated expansion.
+fn replace_recursive<D: Doc>(
+  node: Node<D>,
+  rules: &[(&str, &RuleCore<D::Lang>)],
+  parent_env: &MetaVarEnv<'_, D>,
+  sort_by: Option<&str>,
+  edits: &mut Vec<KeyedEdit<D>>,
+) -> Result<(), RewriteError> {
+  let mut guards = vec![];
+  for (id, rule) in rules {
+    let Some(mut nm) = rule.match_node(node.clone()) else {
+      continue;
+    };
+    // so a nested rewriter's fixer, or a further rewriter it invokes, can
+    // reference metavariables bound by the outer match
+    nm.get_env_mut().merge(parent_env.clone());
+    let key = sort_by.and_then(|key| self::sort_key(nm.get_env(), key));
+    guards.push(ActiveGuard::enter(id)?);
+    let edit = nm.make_edit(*rule, rule.fixer.as_ref().expect("TODO"));
+    edits.push(KeyedEdit { key, edit });
+  }
+  for child in node.children() {
+    replace_recursive(child, rules, parent_env, sort_by, edits)?;
   }
-  edits
+  Ok(())
 }
 
 fn make_edit<D: Doc>(
   old_content: &Bytes<D>,
   edits: Vec<Edit<D::Source>>,
   offset: usize,
-) -> Vec<<<D as Doc>::Source as Content>::Underlying> {
+) -> Result<Vec<<<D as Doc>::Source as Content>::Underlying>, RewriteError> {
   let mut new_content = vec![];
   let mut start = 0;
   for edit in edits {
     let pos = edit.position - offset;
+    if pos < start {
+      return Err(RewriteError::Overlapping);
+    }
     new_content.extend_from_slice(&old_content[start..pos]);
     new_content.extend_from_slice(&edit.inserted_text);
     start = pos + edit.deleted_length;
   }
   // add trailing statements
   new_content.extend_from_slice(&old_content[start..]);
-  new_content
+  Ok(new_content)
 }
 
 #[cfg(test)]
@@ -124,7 +234,7 @@ mod test {
     src: &str,
     pat: &str,
     rewriters: &HashMap<String, RuleCore<TypeScript>>,
-  ) -> String {
+  ) -> Result<Option<String>, RewriteError> {
     let grep = TypeScript::Tsx.ast_grep(src);
     let root = grep.root();
     let mut nm = root.find(pat).expect("should find");
@@ -134,7 +244,7 @@ mod test {
       env: nm.get_env_mut(),
       rewriters,
     };
-    rewrite.compute(&mut ctx).expect("should have transforms")
+    rewrite.compute(&mut ctx)
   }
   macro_rules! str_vec {
     ( $($a: expr),* ) => { vec![ $($a.to_string()),* ] };
@@ -156,10 +266,13 @@ mod test {
     let rewrite = Rewriters {
       source: "$A".into(),
       rewrites: str_vec!["rewrite"],
+      sort_by: None,
       join_by: None,
     };
     let rewriters = make_rewriter(&[("rewrite", "{rule: {kind: number}, fix: '114514'}")]);
-    let ret = apply_transformation(rewrite, "log(t(1, 2, 3))", "log($A)", &rewriters);
+    let ret = apply_transformation(rewrite, "log(t(1, 2, 3))", "log($A)", &rewriters)
+      .expect("should not error")
+      .expect("should have transforms");
     assert_eq!(ret, "t(114514, 114514, 114514)");
   }
 
@@ -167,11 +280,55 @@ mod test {
   fn test_perform_multiple_rewrites() {}
 
   #[test]
-  fn test_rewrites_order_and_overlapping() {}
+  fn test_rewrites_order_and_overlapping() {
+    // position order ("3, 1, 2") and value order ("1, 2, 3") diverge here,
+    // so this can only pass if `sort_by` actually orders by $N's value
+    let rewrite = Rewriters {
+      source: "$A".into(),
+      rewrites: str_vec!["rewrite"],
+      sort_by: Some("$N".into()),
+      join_by: Some(",".into()),
+    };
+    let rewriters = make_rewriter(&[("rewrite", "{rule: {kind: number, pattern: $N}, fix: '$N'}")]);
+    let ret = apply_transformation(rewrite, "log(t(3, 1, 2))", "log($A)", &rewriters)
+      .expect("should not error")
+      .expect("should have transforms");
+    assert_eq!(ret, "1,2,3");
+
+    // `call` rewrites `t(1)` and `num` rewrites the `1` inside it, so
+    // splicing both edits back into the source would overlap
+    let rewrite = Rewriters {
+      source: "$A".into(),
+      rewrites: str_vec!["call", "num"],
+      sort_by: None,
+      join_by: None,
+    };
+    let rewriters = make_rewriter(&[
+      ("call", "{rule: {kind: call_expression}, fix: 'x'}"),
+      ("num", "{rule: {kind: number}, fix: 'y'}"),
+    ]);
+    let err = apply_transformation(rewrite, "log(t(1))", "log($A)", &rewriters)
+      .expect_err("overlapping edits must be rejected");
+    assert!(matches!(err, RewriteError::Overlapping));
+  }
 
   #[test]
   fn test_rewrites_join_by() {}
 
   #[test]
-  fn test_recursive_rewrites() {}
+  fn test_recursive_rewrites() {
+    // the rewriter matches `kind: call_expression`, and `t(f(1))` nests one
+    // call expression inside another, so the same id matches again while
+    // still descending into its own first match
+    let rewrite = Rewriters {
+      source: "$A".into(),
+      rewrites: str_vec!["rewrite"],
+      sort_by: None,
+      join_by: None,
+    };
+    let rewriters = make_rewriter(&[("rewrite", "{rule: {kind: call_expression}, fix: 'x'}")]);
+    let err = apply_transformation(rewrite, "log(t(f(1)))", "log($A)", &rewriters)
+      .expect_err("nested self-match must be rejected as recursive");
+    assert!(matches!(err, RewriteError::Recursive(id) if id == "rewrite"));
+  }
 }